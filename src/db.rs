@@ -4,4 +4,6 @@
 pub mod open;
 pub mod job;
 /// Stream and validate job request messages
-pub mod ingest;
\ No newline at end of file
+pub mod ingest;
+/// Apply versioned schema migrations
+pub mod migrator;
\ No newline at end of file
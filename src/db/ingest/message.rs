@@ -1,23 +1,60 @@
-use anyhow::Result;
-use log::info;
-use rusqlite::Connection;
+use rusqlite::{Connection, OptionalExtension};
+use serde_json::Value;
+use tracing::info;
 
+use crate::error::HattivattiError;
 use crate::request::message::AllasMessage;
 
-/// Load an AllasMessage into a database
+/// What happened when a message was ingested
+#[derive(Debug, PartialEq, Eq)]
+pub enum IngestOutcome {
+    /// No job with this INTERVENE id existed yet; a new row was inserted
+    Ingested,
+    /// A job with this INTERVENE id already existed with different content; its manifest was refreshed
+    Updated,
+    /// A job with this INTERVENE id already existed with identical content; nothing was written
+    Skipped,
+}
+
+/// Load an AllasMessage into a database, keyed on its INTERVENE id
 ///
 /// The AllasMessage is stored in a JSON column and the schema will automatically extract the
-/// INTERVENE ID and add an insertion timestamp
-pub fn ingest_message(conn: &Connection, message: &AllasMessage) -> Result<()> {
-    info!("Adding {} to db", &message.key);
+/// INTERVENE ID and add an insertion timestamp. Re-ingesting the same INTERVENE id doesn't create
+/// a duplicate row: `intervene_id` is uniquely indexed ([`crate::db::migrator`]'s `0001_init`), so
+/// a conflicting insert only refreshes the existing row, and only when the content actually
+/// changed (Allas messages don't carry their own revision timestamp to compare against).
+pub fn ingest_message(conn: &Connection, message: &AllasMessage) -> Result<IngestOutcome, HattivattiError> {
+    let intervene_id = extract_intervene_id(&message.content).unwrap_or_else(|| "unknown".to_string());
+    let span = tracing::info_span!("ingest", intervene_id = %intervene_id, key = %message.key);
+    let _guard = span.enter();
+
     let json = &message.content;
     let valid = &message.valid;
 
+    let existing: Option<String> = conn.query_row(
+        "SELECT manifest FROM job WHERE intervene_id = ?1",
+        [&intervene_id],
+        |row| row.get(0),
+    ).optional()?;
+
+    if existing.as_deref() == Some(json.as_str()) {
+        info!("{} already ingested with unchanged content, skipping", message.key);
+        return Ok(IngestOutcome::Skipped);
+    }
+
+    info!("Adding {} to db", &message.key);
     conn.execute(
-        "INSERT INTO job (manifest, valid) VALUES (?1, ?2)",
+        "INSERT INTO job (manifest, valid) VALUES (?1, ?2) \
+         ON CONFLICT(intervene_id) DO UPDATE SET \
+            manifest = excluded.manifest, valid = excluded.valid, inserted_at = CURRENT_TIMESTAMP",
         (json, valid),
-    )
-        .expect("Error inserting job");
+    )?;
+
+    Ok(if existing.is_some() { IngestOutcome::Updated } else { IngestOutcome::Ingested })
+}
 
-    Ok(())
+/// Best-effort extraction of the INTERVENE id, for the tracing span and for looking up an existing row
+fn extract_intervene_id(content: &str) -> Option<String> {
+    let value: Value = serde_json::from_str(content).ok()?;
+    value.get("pipeline_param")?.get("id")?.as_str().map(str::to_string)
 }
@@ -6,4 +6,10 @@
 
 pub mod load;
 pub mod update;
-pub mod state;
\ No newline at end of file
+pub mod state;
+/// Reconcile stored job state against the SLURM scheduler
+pub mod monitor;
+/// Read-only job queries backing the `list` and `show` subcommands
+pub mod query;
+/// Operator actions (`cancel`, `resubmit`) that change job state outside the normal pipeline
+pub mod manage;
\ No newline at end of file
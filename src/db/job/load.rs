@@ -1,36 +1,58 @@
-use log::info;
+use tracing::{info, warn};
 use rusqlite::Connection;
-use serde_json::Result as JsonResult;
 
+use crate::error::HattivattiError;
 use crate::slurm::job_request::JobRequest;
 
+/// Load every valid, not-yet-staged job from the database, deserialising each manifest
+///
+/// A manifest that fails to deserialise doesn't abort the whole batch: it's marked invalid and the
+/// error is logged, so it's skipped on future polls, and every other job still loads. The error
+/// itself isn't stored in the database.
 pub fn get_valid_jobs(conn: &Connection, dry_run: bool) -> Option<Vec<JobRequest>> {
-    let mut stmt = conn.prepare("SELECT manifest FROM job WHERE valid == 1 AND staged == 0 AND submitted == 0").expect("");
-    let rows = stmt.query_map([], |row| row.get(0)).expect("");
+    let mut stmt = conn.prepare(
+        "SELECT intervene_id, manifest FROM job WHERE valid == 1 AND state == 'initialised'",
+    ).expect("prepare load query");
+    let rows = stmt.query_map([], |row| {
+        let intervene_id: String = row.get(0)?;
+        let manifest: String = row.get(1)?;
+        Ok((intervene_id, manifest))
+    }).expect("query valid jobs");
 
-    let mut json: Vec<String> = Vec::new();
+    let mut jobs: Vec<JobRequest> = Vec::new();
     for row in rows {
-        let json_string: String = row.expect("");
-        info!("Loading valid job from db: {} ...", &json_string[..50]);
-        json.push(json_string);
+        let (intervene_id, manifest) = row.expect("row");
+        info!("Loading valid job from db: {} ...", &manifest[..50.min(manifest.len())]);
+        match deserialise(&intervene_id, &manifest) {
+            Ok(job) => jobs.push(job),
+            Err(err) => {
+                warn!("{intervene_id} has an invalid manifest, marking invalid: {err}");
+                invalidate(conn, &intervene_id);
+            }
+        }
     }
 
     release_or_rollback(&conn, dry_run);
 
-    let jobs = deserialise(json).expect("Deserialised JSON");
     match jobs.is_empty() {
         true => { None }
         false => { Some(jobs) }
     }
 }
 
-fn deserialise(json_strings: Vec<String>) -> JsonResult<Vec<JobRequest>> {
-    let mut jobs: Vec<JobRequest> = Vec::new();
-    for string in json_strings {
-        let job: JobRequest = serde_json::from_str(&string)?;
-        jobs.push(job);
-    }
-    Ok(jobs)
+fn deserialise(intervene_id: &str, manifest: &str) -> Result<JobRequest, HattivattiError> {
+    serde_json::from_str(manifest).map_err(|source| HattivattiError::Deserialise {
+        intervene_id: intervene_id.to_string(),
+        source,
+    })
+}
+
+/// Mark a job invalid so it's no longer picked up by future polls
+fn invalidate(conn: &Connection, intervene_id: &str) {
+    conn.execute(
+        "UPDATE job SET valid = 0 WHERE intervene_id = ?1",
+        [intervene_id],
+    ).expect("invalidate job");
 }
 
 fn release_or_rollback(conn: &Connection, dry_run: bool) {
@@ -0,0 +1,132 @@
+//! Operator actions that change a job's state outside the normal ingest/submit pipeline
+
+use std::process::Command;
+
+use tracing::{info, warn};
+use rusqlite::{Connection, OptionalExtension};
+
+use crate::db::job::state::JobState;
+use crate::db::job::update::set_state;
+use crate::error::HattivattiError;
+use crate::slurm::job_request::JobRequest;
+use crate::WorkingDirectory;
+
+/// Issue `scancel` for a job's SLURM id, then transition it to [JobState::Cancelled]
+///
+/// Runs inside its own `dry_run` savepoint so `--dry-run` rolls back the state change, mirroring
+/// how `poll` honours `--dry-run` (see [`crate::db::job::load::get_valid_jobs`]). In a dry run
+/// `scancel` itself isn't invoked either, since it's a real, irreversible scheduler action.
+pub fn cancel_job(conn: &Connection, intervene_id: &str, dry_run: bool) -> Result<(), HattivattiError> {
+    in_savepoint(conn, dry_run, || {
+        let found: Option<Option<String>> = conn.query_row(
+            "SELECT slurm_id FROM job WHERE intervene_id = ?1",
+            [intervene_id],
+            |row| row.get(0),
+        ).optional()?;
+
+        let slurm_id = match found {
+            Some(slurm_id) => slurm_id,
+            None => {
+                warn!("No job found with INTERVENE id {intervene_id}");
+                return Ok(());
+            }
+        };
+
+        match slurm_id {
+            Some(slurm_id) if dry_run => {
+                info!("--dry-run set, not cancelling SLURM job {slurm_id} for {intervene_id}");
+            }
+            Some(slurm_id) => {
+                info!("Cancelling SLURM job {slurm_id} for {intervene_id}");
+                let status = Command::new("scancel").arg(&slurm_id).status()?;
+                if !status.success() {
+                    warn!("scancel exited with {status}");
+                }
+            }
+            None => warn!("{intervene_id} has no SLURM id yet, cancelling in the database only"),
+        }
+
+        set_state(conn, intervene_id, JobState::Cancelled);
+        Ok(())
+    })
+}
+
+/// Reset a failed job back to [JobState::Initialised] so the next `poll` re-stages and resubmits it
+///
+/// Unlike `resubmit`, this doesn't re-render templates or call `sbatch` itself: it just clears the
+/// job back to the state [`crate::db::job::load::get_valid_jobs`] looks for, so retrying a job goes
+/// through the same path as a first submission. Runs inside its own `dry_run` savepoint.
+pub fn retry_job(conn: &Connection, intervene_id: &str, dry_run: bool) -> Result<(), HattivattiError> {
+    in_savepoint(conn, dry_run, || {
+        let changed = conn.execute(
+            "UPDATE job SET state = ?1, state_changed_at = CURRENT_TIMESTAMP, last_notified_state = NULL \
+             WHERE intervene_id = ?2 AND state = 'failed'",
+            (JobState::Initialised.to_string(), intervene_id),
+        )?;
+
+        if changed == 0 {
+            warn!("{intervene_id} is not in a failed state, not retrying");
+        } else {
+            info!("{intervene_id} reset to initialised for retry");
+        }
+        Ok(())
+    })
+}
+
+/// Re-render a job's templates and resubmit it to SLURM
+///
+/// Runs inside its own `dry_run` savepoint, and skips the actual `sbatch` call in a dry run, same
+/// as `poll` does for first-time submission.
+pub fn resubmit_job(conn: &Connection, wd: &WorkingDirectory, intervene_id: &str, dry_run: bool) -> Result<(), HattivattiError> {
+    in_savepoint(conn, dry_run, || {
+        let manifest: Option<String> = conn.query_row(
+            "SELECT manifest FROM job WHERE intervene_id = ?1",
+            [intervene_id],
+            |row| row.get(0),
+        ).optional()?;
+
+        let manifest = match manifest {
+            Some(manifest) => manifest,
+            None => {
+                warn!("No job found with INTERVENE id {intervene_id}");
+                return Ok(());
+            }
+        };
+
+        let job: JobRequest = serde_json::from_str(&manifest).map_err(|source| HattivattiError::Deserialise {
+            intervene_id: intervene_id.to_string(),
+            source,
+        })?;
+
+        info!("Re-rendering templates for {intervene_id}");
+        let job_path = job.create(wd);
+        job.stage(conn);
+
+        if dry_run {
+            info!("--dry-run set, not resubmitting {intervene_id} to slurm");
+        } else {
+            job.submit(conn, job_path)?;
+        }
+        Ok(())
+    })
+}
+
+/// Run an operator action inside its own savepoint, rolling it back instead of releasing it when
+/// `dry_run` is set
+///
+/// Mirrors the `dry_run` savepoint `poll` wraps ingest and job loading in, so the `cancel`, `retry`,
+/// and `resubmit` subcommands can honour `--dry-run` the same way.
+fn in_savepoint(conn: &Connection, dry_run: bool, f: impl FnOnce() -> Result<(), HattivattiError>) -> Result<(), HattivattiError> {
+    conn.execute("SAVEPOINT dry_run", [])?;
+
+    let result = f();
+
+    if dry_run {
+        info!("--dry-run set, rolling back database state");
+        conn.execute("ROLLBACK TO dry_run", [])?;
+    } else {
+        conn.execute("RELEASE dry_run", [])?;
+    }
+
+    result
+}
@@ -0,0 +1,126 @@
+//! Reconcile stored job state against the SLURM scheduler
+//!
+//! `sacct` is authoritative for jobs that have left the queue and reports an exit code `squeue`
+//! doesn't, so it's tried first. `squeue` is a fallback for jobs `sacct` hasn't recorded yet (for
+//! example a very recent submission on a scheduler with slow accounting). A job neither command
+//! can resolve is marked [JobState::Unknown] instead of left dangling, so operators can find it
+//! with `hattivatti list`.
+
+use std::process::Command;
+
+use rusqlite::Connection;
+use tracing::{info, warn};
+
+use crate::db::job::state::JobState;
+
+/// A job tracked in the database that has a SLURM id and isn't in a terminal state yet
+struct MonitoredJob {
+    intervene_id: String,
+    slurm_id: String,
+}
+
+/// A resolved SLURM outcome: the mapped state, and an exit code if one was reported
+struct Resolved {
+    state: JobState,
+    exit_code: Option<i64>,
+}
+
+/// Poll SLURM for every non-terminal job with a recorded `slurm_id` and update its stored state
+///
+/// Safe to call repeatedly: a job whose SLURM state hasn't changed since the last poll causes no
+/// database write, and terminal states are never overwritten once recorded.
+pub fn poll_jobs(conn: &Connection) {
+    for job in jobs_to_monitor(conn) {
+        let resolved = resolve_state(&job.slurm_id).unwrap_or_else(|| {
+            warn!("Couldn't resolve SLURM state for job {} ({})", job.intervene_id, job.slurm_id);
+            Resolved { state: JobState::Unknown, exit_code: None }
+        });
+        apply_state(conn, &job, resolved);
+    }
+}
+
+fn jobs_to_monitor(conn: &Connection) -> Vec<MonitoredJob> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT intervene_id, slurm_id FROM job \
+         WHERE slurm_id IS NOT NULL \
+         AND {}",
+        JobState::non_terminal_sql(),
+    )).expect("prepare monitor query");
+
+    stmt.query_map([], |row| {
+        Ok(MonitoredJob { intervene_id: row.get(0)?, slurm_id: row.get(1)? })
+    })
+        .expect("query monitored jobs")
+        .filter_map(Result::ok)
+        .collect()
+}
+
+/// Resolve a SLURM job id, preferring job accounting (which has an exit code) over the live queue
+fn resolve_state(slurm_id: &str) -> Option<Resolved> {
+    sacct_state(slurm_id).or_else(|| squeue_state(slurm_id))
+}
+
+/// Run `sacct` and parse the top-level job line, ignoring `.batch`/`.extern` sub-steps
+fn sacct_state(slurm_id: &str) -> Option<Resolved> {
+    let output = Command::new("sacct")
+        .args(["-j", slurm_id, "--parsable2", "--noheader", "--format=JobID,State,ExitCode"])
+        .output()
+        .ok()?;
+
+    let text = String::from_utf8_lossy(&output.stdout);
+    let line = text.lines().find(|line| is_top_level_step(line, slurm_id))?;
+
+    let mut fields = line.split('|');
+    fields.next()?; // JobID, already matched against slurm_id
+    let state_field = fields.next()?.trim();
+    let exit_field = fields.next().unwrap_or("").trim();
+
+    let state = JobState::from_slurm_state(state_field)?;
+    let exit_code = exit_field.split(':').next().and_then(|code| code.parse().ok());
+
+    Some(Resolved { state, exit_code })
+}
+
+/// `sacct` reports one line per job step (`<id>`, `<id>.batch`, `<id>.extern`, ...); only the
+/// bare job id line reflects the overall job outcome
+fn is_top_level_step(line: &str, slurm_id: &str) -> bool {
+    line.split('|').next() == Some(slurm_id)
+}
+
+/// Fall back to `squeue` for jobs `sacct` hasn't recorded yet; `squeue` has no exit code
+fn squeue_state(slurm_id: &str) -> Option<Resolved> {
+    let output = Command::new("squeue")
+        .args(["-j", slurm_id, "--noheader", "--format=%T"])
+        .output()
+        .ok()?;
+
+    let state = String::from_utf8_lossy(&output.stdout);
+    let state = state.lines().next()?.trim();
+    if state.is_empty() { return None; }
+    JobState::from_slurm_state(state).map(|state| Resolved { state, exit_code: None })
+}
+
+/// Write a resolved outcome to the database inside a transaction, unless the job has already
+/// reached a terminal state or is already recorded as being in that state
+fn apply_state(conn: &Connection, job: &MonitoredJob, resolved: Resolved) {
+    let new_state = resolved.state.to_string();
+
+    conn.execute("BEGIN", []).expect("begin monitor transaction");
+
+    let changed = conn.execute(
+        &format!(
+            "UPDATE job SET state = ?1, state_changed_at = CURRENT_TIMESTAMP, \
+             exit_code = COALESCE(?2, exit_code) \
+             WHERE intervene_id = ?3 \
+             AND {} AND state != ?1",
+            JobState::non_terminal_sql(),
+        ),
+        (new_state, resolved.exit_code, job.intervene_id.as_str()),
+    ).expect("Update job state from monitor");
+
+    conn.execute("COMMIT", []).expect("commit monitor transaction");
+
+    if changed > 0 {
+        info!("{} transitioned to {}", job.intervene_id, new_state);
+    }
+}
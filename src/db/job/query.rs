@@ -0,0 +1,56 @@
+//! Read-only job queries backing the `list` and `show` subcommands
+
+use tracing::{info, warn};
+use rusqlite::Connection;
+
+use crate::db::job::state::JobState;
+use crate::slurm::job_request::JobRequest;
+
+/// Print every job's INTERVENE id, state, SLURM id, and exit code, optionally filtered to one state
+pub fn list_jobs(conn: &Connection, state: Option<&str>) {
+    let state = match state.map(JobState::from_db_str) {
+        Some(None) => {
+            warn!("'{}' isn't a known job state", state.unwrap());
+            return;
+        }
+        Some(Some(state)) => Some(state),
+        None => None,
+    };
+
+    let mut stmt = conn.prepare(
+        "SELECT intervene_id, state, slurm_id, exit_code FROM job \
+         WHERE ?1 IS NULL OR state == ?1 ORDER BY id",
+    ).expect("prepare list query");
+
+    let rows = stmt.query_map([state.map(|state| state.to_string())], |row| {
+        let intervene_id: String = row.get(0)?;
+        let state: String = row.get(1)?;
+        let slurm_id: Option<String> = row.get(2)?;
+        let exit_code: Option<i64> = row.get(3)?;
+        Ok((intervene_id, state, slurm_id, exit_code))
+    }).expect("query jobs");
+
+    println!("{:<40} {:<12} {:<12} {}", "INTERVENE ID", "STATE", "SLURM ID", "EXIT CODE");
+    for (intervene_id, state, slurm_id, exit_code) in rows.filter_map(Result::ok) {
+        let slurm_id = slurm_id.unwrap_or_else(|| "-".to_string());
+        let exit_code = exit_code.map(|code| code.to_string()).unwrap_or_else(|| "-".to_string());
+        println!("{:<40} {:<12} {:<12} {}", intervene_id, state, slurm_id, exit_code);
+    }
+}
+
+/// Print a job's stored manifest, deserialised into a [JobRequest]
+pub fn show_job(conn: &Connection, intervene_id: &str) {
+    let manifest: Option<String> = conn.query_row(
+        "SELECT manifest FROM job WHERE intervene_id = ?1",
+        [intervene_id],
+        |row| row.get(0),
+    ).ok();
+
+    match manifest {
+        Some(manifest) => {
+            let job: JobRequest = serde_json::from_str(&manifest).expect("Valid stored manifest");
+            println!("{job:#?}");
+        }
+        None => info!("No job found with INTERVENE id {intervene_id}"),
+    }
+}
@@ -1,20 +1,94 @@
+/// The lifecycle of a job submitted by `hattivatti`
+///
+/// Jobs move from [JobState::Initialised] (request ingested but not yet rendered) through
+/// staging and SLURM submission, then into the states reported by `sacct`/`squeue`, finishing in
+/// one of the terminal states. Terminal states must never be overwritten once recorded: once a
+/// job is `Succeeded`, `Failed`, `Cancelled` or `TimedOut` the monitor leaves it alone.
+/// `Unknown` marks a job neither `sacct` nor `squeue` could resolve; it's deliberately not
+/// terminal, so the monitor keeps retrying it on later polls rather than leaving it dangling.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum JobState {
+    Initialised,
     Staged,
-    Submitted
+    Submitted,
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+    TimedOut,
+    Unknown,
 }
 
-/// A simple way to keep track of job state.
-///
-/// Currently only two states are supported: staged (rendered templates written to disk) and
-/// submitted (after sbatch system command exits 0). Other job states could include things like
-/// INITIALISED (request received) or PENDING (parsing squeue output) in the future.
 impl JobState {
-    /// db columns are all lower case, enum used in sql statement
-    /// TODO: migrate to a single enum column called "state"
-    pub fn to_string(&self) -> &str {
+    /// db stores state as lower case text in a single `state` column
+    pub fn to_string(&self) -> &'static str {
         match self {
+            JobState::Initialised => "initialised",
             JobState::Staged => "staged",
-            JobState::Submitted => "submitted"
+            JobState::Submitted => "submitted",
+            JobState::Pending => "pending",
+            JobState::Running => "running",
+            JobState::Succeeded => "succeeded",
+            JobState::Failed => "failed",
+            JobState::Cancelled => "cancelled",
+            JobState::TimedOut => "timed_out",
+            JobState::Unknown => "unknown",
+        }
+    }
+
+    /// Parse a state back out of the `state` column
+    pub fn from_db_str(state: &str) -> Option<JobState> {
+        match state {
+            "initialised" => Some(JobState::Initialised),
+            "staged" => Some(JobState::Staged),
+            "submitted" => Some(JobState::Submitted),
+            "pending" => Some(JobState::Pending),
+            "running" => Some(JobState::Running),
+            "succeeded" => Some(JobState::Succeeded),
+            "failed" => Some(JobState::Failed),
+            "cancelled" => Some(JobState::Cancelled),
+            "timed_out" => Some(JobState::TimedOut),
+            "unknown" => Some(JobState::Unknown),
+            _ => None,
         }
     }
-}
\ No newline at end of file
+
+    /// A terminal state is a job's final word: the monitor must never transition out of it
+    pub fn is_terminal(&self) -> bool {
+        Self::terminal_states().contains(self)
+    }
+
+    /// Every terminal state, the single source of truth backing [`JobState::is_terminal`]
+    fn terminal_states() -> [JobState; 4] {
+        [JobState::Succeeded, JobState::Failed, JobState::Cancelled, JobState::TimedOut]
+    }
+
+    /// A `state NOT IN (...)` SQL fragment matching every terminal state's db encoding
+    ///
+    /// Used to guard writes against overwriting a terminal state without hardcoding the state
+    /// list separately in every query that needs the guard.
+    pub fn non_terminal_sql() -> String {
+        let states: Vec<String> = Self::terminal_states().iter()
+            .map(|state| format!("'{}'", state.to_string()))
+            .collect();
+        format!("state NOT IN ({})", states.join(", "))
+    }
+
+    /// Map a `squeue --format=%T` or `sacct --format=State` textual state onto a [JobState]
+    ///
+    /// Returns `None` for SLURM states hattivatti doesn't need to track separately (e.g.
+    /// `SUSPENDED`, `CONFIGURING`), so callers can leave the stored state unchanged.
+    pub fn from_slurm_state(state: &str) -> Option<JobState> {
+        let state = state.trim();
+        match state {
+            "PENDING" => Some(JobState::Pending),
+            "RUNNING" | "COMPLETING" => Some(JobState::Running),
+            "COMPLETED" => Some(JobState::Succeeded),
+            "FAILED" | "NODE_FAIL" | "BOOT_FAIL" | "OUT_OF_MEMORY" => Some(JobState::Failed),
+            "TIMEOUT" => Some(JobState::TimedOut),
+            _ if state.starts_with("CANCELLED") => Some(JobState::Cancelled),
+            _ => None,
+        }
+    }
+}
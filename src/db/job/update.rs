@@ -1,50 +1,45 @@
 use std::path::Path;
 use std::process::Command;
-use log::info;
+use tracing::info;
 use rusqlite::Connection;
 
 use crate::db::job::state::JobState;
+use crate::error::HattivattiError;
 use crate::slurm::job::JobPath;
 use crate::slurm::job_request::JobRequest;
 
 impl JobRequest {
+    #[tracing::instrument(name = "stage", skip_all, fields(intervene_id = %self.pipeline_param.id))]
     pub fn stage(&self, conn: &Connection) {
         let state = JobState::Staged;
         self.update(conn, state);
     }
 
-    pub fn submit(&self, conn: &Connection, job: JobPath) {
-        let job_id = self.run_sbatch(job);
+    #[tracing::instrument(name = "submit", skip_all, fields(intervene_id = %self.pipeline_param.id))]
+    pub fn submit(&self, conn: &Connection, job: JobPath) -> Result<(), HattivattiError> {
+        let job_id = self.run_sbatch(job)?;
         info!("SLURM job id: {job_id}");
         let state = JobState::Submitted;
         self.update(conn, state);
-        self.update_slurm(conn, job_id).expect("update OK");
+        self.update_slurm(conn, job_id)?;
+        Ok(())
     }
 
-    fn update_slurm(&self, conn: &Connection, slurm_id: String) -> rusqlite::Result<()> {
+    fn update_slurm(&self, conn: &Connection, slurm_id: String) -> Result<(), HattivattiError> {
         let id = &self.pipeline_param.id.to_string();
         info!("Updating {id} with slurm ID {slurm_id}");
-        conn
-            .execute("UPDATE job SET slurm_id = ? WHERE intervene_id = ?",
-            &[&slurm_id, &id])
-            .expect("Update");
+        conn.execute("UPDATE job SET slurm_id = ? WHERE intervene_id = ?",
+            &[&slurm_id, &id])?;
 
         Ok(())
     }
 
     fn update(&self, conn: &Connection, state: JobState) {
-        let id = &self.pipeline_param.id.to_string();
-        let col = state.to_string();
-        info!("Updating {id} with state {col}");
-        let stmt = format!("UPDATE job SET {col} = 1 WHERE intervene_id = (?1)");
-
-        conn.execute(
-            &stmt,
-            &[(id.as_str())],
-        ).expect("Update job status to {col}");
+        let id = self.pipeline_param.id.to_string();
+        set_state(conn, &id, state);
     }
 
-    fn run_sbatch(&self, job_path: JobPath) -> String {
+    fn run_sbatch(&self, job_path: JobPath) -> Result<String, HattivattiError> {
         let wd = job_path.path.parent().unwrap();
         let output_path = wd.join(Path::new("%j.out"));
         let output_str = output_path.to_str().unwrap();
@@ -56,8 +51,40 @@ impl JobRequest {
         let mut sbatch = Command::new("sbatch");
         let cmd = sbatch.args(&arguments);
         info!("{:?}", &cmd);
-        let output = cmd.output().expect("failed to execute process").stdout;
+        let output = cmd.output()?;
+
+        if !output.status.success() {
+            return Err(HattivattiError::Sbatch {
+                status: output.status,
+                stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            });
+        }
+
+        let job_id = String::from_utf8(output.stdout).expect("sbatch --parsable prints a plain job id");
+        // trim the trailing newline sbatch --parsable prints after the id: Command bypasses the
+        // shell, so this would otherwise be passed byte-for-byte as a -j argument to sacct/squeue
+        Ok(job_id.trim().to_string())
+    }
+}
+
+/// Transition a job to `state` by its INTERVENE id, guarding against overwriting a terminal state
+///
+/// Shared with the `cancel`/`resubmit` management subcommands ([`crate::db::job::manage`])
+/// so operator-triggered transitions use the same guard as the ingest/submit pipeline.
+pub fn set_state(conn: &Connection, intervene_id: &str, state: JobState) {
+    let new_state = state.to_string();
+    info!("Updating {intervene_id} with state {new_state}");
+
+    let changed = conn.execute(
+        &format!(
+            "UPDATE job SET state = ?1, state_changed_at = CURRENT_TIMESTAMP \
+             WHERE intervene_id = ?2 AND {}",
+            JobState::non_terminal_sql(),
+        ),
+        (new_state, intervene_id),
+    ).expect("Update job state");
 
-        String::from_utf8(output).expect("job id")
+    if changed == 0 {
+        info!("{intervene_id} already in a terminal state, not overwriting with {new_state}");
     }
 }
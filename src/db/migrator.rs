@@ -0,0 +1,148 @@
+//! Apply versioned database schema migrations
+//!
+//! `open_db` used to create the schema implicitly with a single `CREATE TABLE IF NOT EXISTS`
+//! statement, which has no way to evolve once the schema needs a new column. Migrations are
+//! embedded SQL scripts, applied once each in ascending order inside a transaction, and recorded
+//! in `schema_migrations` so a migration is never applied twice.
+
+use std::fmt;
+
+use tracing::info;
+use rusqlite::Connection;
+
+/// A single embedded migration
+///
+/// `version` must be unique and the `MIGRATIONS` list must stay in ascending order: migrations
+/// are applied in list order and recorded under their `version`, so reordering or renumbering a
+/// migration that's already shipped will desync deployed databases from this binary.
+struct Migration {
+    version: i64,
+    name: &'static str,
+    sql: &'static str,
+}
+
+/// Ordered list of migrations to apply. Add new migrations to the end; never edit, reorder, or
+/// renumber a migration that's already been released.
+static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "init",
+        sql: include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/data/db/migrations/0001_init.sql")),
+    },
+    Migration {
+        version: 2,
+        name: "add_state",
+        sql: include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/data/db/migrations/0002_add_state.sql")),
+    },
+    Migration {
+        version: 3,
+        name: "add_notifier",
+        sql: include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/data/db/migrations/0003_add_notifier.sql")),
+    },
+    Migration {
+        version: 4,
+        name: "add_exit_code",
+        sql: include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/data/db/migrations/0004_add_exit_code.sql")),
+    },
+];
+
+/// Errors raised while applying migrations
+#[derive(Debug)]
+pub enum MigrationError {
+    /// A migration failed to apply and its transaction was rolled back
+    Db(rusqlite::Error),
+    /// A migration recorded in `schema_migrations` doesn't match the one compiled into this
+    /// binary, or the database has a version this binary doesn't know about
+    Mismatch(String),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MigrationError::Db(e) => write!(f, "migration failed: {e}"),
+            MigrationError::Mismatch(msg) => write!(f, "migration mismatch: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+impl From<rusqlite::Error> for MigrationError {
+    fn from(e: rusqlite::Error) -> Self {
+        MigrationError::Db(e)
+    }
+}
+
+/// Create the `schema_migrations` table (if needed) and apply every migration not yet recorded
+pub fn run_migrations(conn: &Connection) -> Result<(), MigrationError> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (\
+            version INTEGER PRIMARY KEY, \
+            name TEXT NOT NULL, \
+            applied_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP\
+        )",
+        [],
+    )?;
+
+    let applied = applied_migrations(conn)?;
+
+    for (version, name) in &applied {
+        match MIGRATIONS.iter().find(|m| m.version == *version) {
+            Some(migration) if migration.name == name => {}
+            Some(migration) => {
+                return Err(MigrationError::Mismatch(format!(
+                    "version {version} was applied as '{name}' but this binary knows it as '{}'",
+                    migration.name
+                )));
+            }
+            None => {
+                return Err(MigrationError::Mismatch(format!(
+                    "database has migration {version} ('{name}') this binary doesn't know about"
+                )));
+            }
+        }
+    }
+
+    let highest_applied = applied.iter().map(|(v, _)| *v).max().unwrap_or(0);
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > highest_applied) {
+        info!("Applying migration {:04}_{}", migration.version, migration.name);
+        apply(conn, migration)?;
+    }
+
+    Ok(())
+}
+
+fn applied_migrations(conn: &Connection) -> Result<Vec<(i64, String)>, MigrationError> {
+    let mut stmt = conn.prepare("SELECT version, name FROM schema_migrations ORDER BY version")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<Vec<(i64, String)>>>()?;
+    Ok(rows)
+}
+
+/// Apply one migration's SQL and record it, rolling the whole transaction back on failure
+fn apply(conn: &Connection, migration: &Migration) -> Result<(), MigrationError> {
+    conn.execute("BEGIN", [])?;
+
+    let result: Result<(), rusqlite::Error> = conn
+        .execute_batch(migration.sql)
+        .and_then(|_| {
+            conn.execute(
+                "INSERT INTO schema_migrations (version, name) VALUES (?1, ?2)",
+                (migration.version, migration.name),
+            )
+            .map(|_| ())
+        });
+
+    match result {
+        Ok(()) => {
+            conn.execute("COMMIT", [])?;
+            Ok(())
+        }
+        Err(e) => {
+            conn.execute("ROLLBACK", [])?;
+            Err(MigrationError::Db(e))
+        }
+    }
+}
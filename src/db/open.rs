@@ -1,18 +1,20 @@
-use log::info;
+use tracing::info;
+use crate::db::migrator;
 use crate::WorkingDirectory;
 
 /// Open a connection to an existing database, or create a new one if it doesn't exist
+///
+/// The schema is brought up to date by running any pending migrations (see
+/// [`crate::db::migrator`]) before the connection is handed back.
 pub fn open_db(wd: &WorkingDirectory) -> rusqlite::Result<rusqlite::Connection> {
     let path = &wd.path.join("hattivatti.db");
     if !path.exists() { info!("Creating new database {}", path.display()) }
     let conn = rusqlite::Connection::open(&path)?;
 
-    /// A SQLite database schema that stores job status
-    static SCHEMA: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/data/db/schema.sql"));
-    conn.execute(SCHEMA, [], )?;
+    conn.pragma_update(None, "journal_mode", "WAL").expect("Enable WAL mode");
+    conn.pragma_update(None, "foreign_keys", true).expect("Enable foreign keys");
 
-    info!("Creating dry run save point");
-    conn.execute("SAVEPOINT dry_run", []).expect("Start transaction");
+    migrator::run_migrations(&conn).expect("Apply database migrations");
 
     Ok(conn)
 }
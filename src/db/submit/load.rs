@@ -1,4 +1,4 @@
-use log::info;
+use tracing::info;
 use rusqlite::Connection;
 use serde_json::Result as JsonResult;
 use crate::slurm::job_request::JobRequest;
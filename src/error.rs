@@ -0,0 +1,40 @@
+//! Crate-wide error type
+//!
+//! Ingest and submission used to `.expect()` on every fallible call, so one malformed manifest or
+//! a missing `sbatch` binary would take down an entire poll that was otherwise processing dozens
+//! of valid jobs. Functions on that path return [`HattivattiError`] instead, so callers like
+//! [`crate::db::job::load::get_valid_jobs`] can catch a single job's failure and keep going.
+
+use std::process::ExitStatus;
+
+use thiserror::Error;
+
+/// Errors raised while ingesting, loading, or submitting jobs
+#[derive(Error, Debug)]
+pub enum HattivattiError {
+    /// A SQLite call failed
+    #[error("database error: {0}")]
+    Db(#[from] rusqlite::Error),
+
+    /// A stored manifest couldn't be deserialised into a [`crate::slurm::job_request::JobRequest`]
+    #[error("couldn't deserialise manifest for {intervene_id}: {source}")]
+    Deserialise {
+        /// The job's INTERVENE id, for attributing the failure in logs
+        intervene_id: String,
+        /// The underlying JSON error
+        source: serde_json::Error,
+    },
+
+    /// `sbatch` exited non-zero when submitting a job
+    #[error("sbatch exited with {status}: {stderr}")]
+    Sbatch {
+        /// The exit status reported by `sbatch`
+        status: ExitStatus,
+        /// `sbatch`'s captured stderr
+        stderr: String,
+    },
+
+    /// A filesystem operation failed
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+}
@@ -1,6 +1,6 @@
 //! `hattivatti` submits [`pgsc_calc`](https://github.com/PGScatalog/pgsc_calc) jobs to
 //! [Puhti HPC](https://docs.csc.fi/computing/systems-puhti/) at CSC. Jobs are configured to execute
-//! in a secure way because genomes are sensitive data. `hattivatti` does the following:
+//! in a secure way because genomes are sensitive data. `hattivatti poll` does the following:
 //!
 //! - Check [Allas](https://docs.csc.fi/data/Allas/) bucket for messages (JSON files)
 //! - Stream messages and validate them with JSON Schema
@@ -8,21 +8,31 @@
 //! - Load valid messages from database and deserialise into [JobRequest]
 //! - Render job templates to [WorkingDirectory]
 //! - Submit jobs with sbatch system command and update the database with `SLURM_JOB_ID`
+//! - Poll SLURM for state updates on submitted jobs
+//! - Notify the INTERVENE backend of state changes
+//!
+//! Operators can also manage the job queue directly with the `list`, `show`, `cancel`,
+//! `resubmit`, and `retry` subcommands instead of waiting for the next `poll`.
 
 #![warn(missing_docs)]
 
 use std::fs;
-use std::path::{PathBuf};
+use std::path::PathBuf;
 
-use clap::Parser;
-use log::info;
+use clap::{Parser, Subcommand};
 use rusqlite::Connection;
+use tracing::{info, warn};
+use tracing_subscriber::prelude::*;
 
-use crate::db::ingest::message::ingest_message;
+use crate::db::ingest::message::{ingest_message, IngestOutcome};
 use crate::db::job::load::get_valid_jobs;
+use crate::namespace::PlatformNamespace;
 use crate::slurm::job_request::JobRequest;
 
 mod db;
+mod error;
+mod namespace;
+mod notifier;
 mod request;
 mod slurm;
 
@@ -37,15 +47,63 @@ processing task to the SLURM scheduler. The program also monitors the state of s
 and notifies the INTERVENE backend when a requested job has succeeded or failed.")]
 /// CLI arguments (automatically parsed by CLAP)
 struct Args {
-    /// A directory path that contains a set of JSON schema to validate messages in the job queue
-    #[arg(short, long)]
-    schema_dir: PathBuf,
     /// A directory where hattivatti can store jobs before submitting them to the SLURM scheduler
     #[arg(short, long)]
     work_dir: PathBuf,
-    /// Read messages from the queue and create SLURM job files, but don't submit them to the SLURM scheduler
-    #[arg(long)]
-    dry_run: bool
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// Operations `hattivatti` can perform against the job queue
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check Allas for new messages, submit valid jobs to SLURM, and report on job state
+    Poll {
+        /// A directory path that contains a set of JSON schema to validate messages in the job queue
+        #[arg(short, long)]
+        schema_dir: PathBuf,
+        /// Which INTERVENE platform environment to notify of job state changes
+        #[arg(short, long)]
+        namespace: PlatformNamespace,
+        /// Read messages from the queue and create SLURM job files, but don't submit them to the SLURM scheduler
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Print every job in the queue with its current state and SLURM id
+    List {
+        /// Only print jobs in this state (e.g. staged, submitted, running, succeeded, failed)
+        #[arg(short, long)]
+        state: Option<String>,
+    },
+    /// Print a job's stored manifest
+    Show {
+        /// The INTERVENE id of the job to show
+        intervene_id: String,
+    },
+    /// Cancel a job's SLURM allocation and mark it as cancelled
+    Cancel {
+        /// The INTERVENE id of the job to cancel
+        intervene_id: String,
+        /// Roll back the state change and don't actually run `scancel`
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Re-render a job's templates and resubmit it to SLURM
+    Resubmit {
+        /// The INTERVENE id of the job to resubmit
+        intervene_id: String,
+        /// Re-render templates but don't actually call `sbatch`, and roll back the state change
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Reset a failed job so the next `poll` re-stages and resubmits it
+    Retry {
+        /// The INTERVENE id of the job to retry
+        intervene_id: String,
+        /// Roll back the state change instead of committing it
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 /// A directory for storing working data
@@ -63,42 +121,88 @@ pub struct WorkingDirectory {
 /// Entrypoint to the program
 #[tokio::main]
 async fn main() {
-    env_logger::init();
-    info!("terve! starting up :)");
-
     let args = Args::parse();
     let wd = WorkingDirectory { path: args.work_dir };
     fs::create_dir_all(&wd.path).expect("Can't create working directory");
 
+    let _log_guard = init_logging(&wd);
+    info!("terve! starting up :)");
+
     let conn: Connection = db::open::open_db(&wd)
         .expect("Database connection");
 
-    let schema = request::schema::load_schema(args.schema_dir.as_path());
+    match args.command {
+        Command::Poll { schema_dir, namespace, dry_run } => poll(&conn, &wd, &schema_dir, namespace, dry_run).await,
+        Command::List { state } => db::job::query::list_jobs(&conn, state.as_deref()),
+        Command::Show { intervene_id } => db::job::query::show_job(&conn, &intervene_id),
+        Command::Cancel { intervene_id, dry_run } => {
+            if let Err(err) = db::job::manage::cancel_job(&conn, &intervene_id, dry_run) {
+                warn!("Failed to cancel {intervene_id}: {err}");
+            }
+        }
+        Command::Resubmit { intervene_id, dry_run } => {
+            if let Err(err) = db::job::manage::resubmit_job(&conn, &wd, &intervene_id, dry_run) {
+                warn!("Failed to resubmit {intervene_id}: {err}");
+            }
+        }
+        Command::Retry { intervene_id, dry_run } => {
+            if let Err(err) = db::job::manage::retry_job(&conn, &intervene_id, dry_run) {
+                warn!("Failed to retry {intervene_id}: {err}");
+            }
+        }
+    }
+
+    info!("finished :D")
+}
+
+/// Ingest queued messages, submit valid jobs, then reconcile and notify on state
+///
+/// Ingest and job loading run inside a `dry_run` savepoint ([`db::job::load::get_valid_jobs`]
+/// releases or rolls it back once jobs are loaded, before staging and submission), so a
+/// `--dry-run` poll never persists the rows it ingests.
+async fn poll(conn: &Connection, wd: &WorkingDirectory, schema_dir: &PathBuf, namespace: PlatformNamespace, dry_run: bool) {
+    conn.execute("SAVEPOINT dry_run", []).expect("Start dry run save point");
+
+    let schema = request::schema::load_schema(schema_dir.as_path());
     let s3_client = request::message::make_allas_client();
     let messages = request::message::fetch_all(&s3_client, &schema).await;
 
     if let Some(messages) = messages {
+        let (mut ingested, mut updated, mut skipped) = (0, 0, 0);
+
         for message in messages {
-            let _ = ingest_message(&conn, &message);
+            match ingest_message(conn, &message) {
+                Ok(IngestOutcome::Ingested) => ingested += 1,
+                Ok(IngestOutcome::Updated) => updated += 1,
+                Ok(IngestOutcome::Skipped) => skipped += 1,
+                Err(err) => {
+                    warn!("Failed to ingest {}: {err}", message.key);
+                    continue;
+                }
+            }
 
-            if !args.dry_run {
+            if !dry_run {
                 message.delete(&s3_client).await;
             } else {
                 info!("--dry-run set, not deleting message in queue");
             }
         }
+
+        info!("Ingested {ingested} new, updated {updated}, skipped {skipped} unchanged");
     } else {
         info!("No new jobs in queue");
     }
 
-    let jobs: Option<Vec<JobRequest>> = get_valid_jobs(&conn, args.dry_run);
+    let jobs: Option<Vec<JobRequest>> = get_valid_jobs(conn, dry_run);
 
     if let Some(jobs) = jobs {
         for job in jobs {
-            let job_path = job.create(&wd);
-            if !args.dry_run {
-                job.stage(&conn);
-                job.submit(&conn, job_path);
+            let job_path = job.create(wd);
+            if !dry_run {
+                job.stage(conn);
+                if let Err(err) = job.submit(conn, job_path) {
+                    warn!("Failed to submit {}: {err}", job.pipeline_param.id);
+                }
             } else {
                 info!("--dry-run set, not submitting job to slurm");
             }
@@ -107,5 +211,34 @@ async fn main() {
         info!("No jobs to load from database");
     }
 
-    info!("finished :D")
+    if !dry_run {
+        info!("Polling SLURM for job state updates");
+        db::job::monitor::poll_jobs(conn);
+    } else {
+        info!("--dry-run set, not polling slurm for state updates");
+    }
+
+    info!("Notifying INTERVENE backend of state changes");
+    let notifier_config = notifier::NotifierConfig::from_env(namespace);
+    notifier::notify_pending(conn, &notifier_config, wd, dry_run).await;
+}
+
+/// Set up console logging plus a daily-rotating JSON-lines file sink under the working directory
+///
+/// Per-job spans (see [`slurm::job`], [`db::job::update`], [`db::ingest::message`]) are attached
+/// to every log line in both layers, so `grep`ing an `intervene_id` finds a job's full lifecycle.
+///
+/// Returns the file appender's worker guard: it must be kept alive for the program's lifetime, or
+/// buffered log lines are dropped before they're flushed to disk.
+fn init_logging(wd: &WorkingDirectory) -> tracing_appender::non_blocking::WorkerGuard {
+    let file_appender = tracing_appender::rolling::daily(&wd.path, "hattivatti.log");
+    let (non_blocking, guard) = tracing_appender::non_blocking(file_appender);
+
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::EnvFilter::from_default_env())
+        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_subscriber::fmt::layer().json().with_writer(non_blocking))
+        .init();
+
+    guard
 }
@@ -0,0 +1,158 @@
+//! Report job state transitions to the INTERVENE backend
+//!
+//! The SLURM job script used to `curl` a callback itself via a bash trap, which could only
+//! distinguish exit 0 from non-zero and silently dropped the notification if the HTTP call
+//! failed. Notifications are sent by hattivatti itself instead, once per state, with retries.
+
+use std::time::Duration;
+
+use chrono::Utc;
+use tracing::{info, warn};
+use reqwest::Client;
+use rusqlite::Connection;
+use serde::Serialize;
+
+use crate::db::job::state::JobState;
+use crate::namespace::PlatformNamespace;
+use crate::WorkingDirectory;
+
+/// Maximum number of delivery attempts before a notification is given up on
+const MAX_ATTEMPTS: u32 = 4;
+
+/// States worth telling the INTERVENE backend about
+const NOTIFIABLE_STATES: [JobState; 4] =
+    [JobState::Pending, JobState::Running, JobState::Succeeded, JobState::Failed];
+
+/// Where and how to deliver notifications for a single `hattivatti poll` run
+///
+/// The endpoint is resolved per [PlatformNamespace] (a dev deployment must never notify the prod
+/// backend and vice versa), read from `INTERVENE_CALLBACK_URL_<NAMESPACE>`.
+pub struct NotifierConfig {
+    namespace: PlatformNamespace,
+    endpoint: String,
+    auth_token: String,
+}
+
+impl NotifierConfig {
+    /// Read the endpoint and auth token for `namespace` from the environment
+    pub fn from_env(namespace: PlatformNamespace) -> NotifierConfig {
+        let var = format!("INTERVENE_CALLBACK_URL_{}", namespace.to_string().to_uppercase());
+        let endpoint = std::env::var(&var).unwrap_or_else(|_| panic!("{var} environment variable not set"));
+        let auth_token = std::env::var("INTERVENE_AUTH_TOKEN")
+            .expect("INTERVENE_AUTH_TOKEN environment variable not set");
+
+        NotifierConfig { namespace, endpoint, auth_token }
+    }
+}
+
+/// A job whose current state hasn't been reported to the backend yet
+struct PendingNotification {
+    intervene_id: String,
+    state: JobState,
+    slurm_id: Option<String>,
+    exit_code: Option<i64>,
+}
+
+/// JSON body POSTed to the INTERVENE backend when a job changes state
+#[derive(Serialize)]
+struct StateNotification<'a> {
+    intervene_id: &'a str,
+    state: &'a str,
+    slurm_job_id: Option<&'a str>,
+    exit_code: Option<i64>,
+    log_path: Option<String>,
+    timestamp: String,
+}
+
+/// Send a notification for every job whose current state hasn't been reported yet
+///
+/// Safe to call repeatedly: a job is only notified once per state, tracked in the
+/// `last_notified_state` column, so restarting hattivatti doesn't resend notifications for
+/// states that have already been delivered. No-ops in `--dry-run`, since dry runs don't submit
+/// jobs and so have no real state transitions to report.
+pub async fn notify_pending(conn: &Connection, config: &NotifierConfig, wd: &WorkingDirectory, dry_run: bool) {
+    if dry_run {
+        info!("--dry-run set, not notifying INTERVENE backend");
+        return;
+    }
+
+    for job in pending_notifications(conn) {
+        let log_path = job.slurm_id.as_deref().map(|slurm_id| output_log_path(wd, &job.intervene_id, slurm_id));
+
+        let payload = StateNotification {
+            intervene_id: &job.intervene_id,
+            state: job.state.to_string(),
+            slurm_job_id: job.slurm_id.as_deref(),
+            exit_code: job.exit_code,
+            log_path,
+            timestamp: Utc::now().to_string(),
+        };
+
+        match send_with_retry(config, &payload).await {
+            Ok(()) => record_notified(conn, &job.intervene_id, job.state),
+            Err(e) => warn!("Giving up notifying {} of state {}: {e}", job.intervene_id, job.state.to_string()),
+        }
+    }
+}
+
+fn pending_notifications(conn: &Connection) -> Vec<PendingNotification> {
+    let mut stmt = conn.prepare(
+        "SELECT intervene_id, state, slurm_id, exit_code FROM job \
+         WHERE last_notified_state IS NULL OR last_notified_state != state",
+    ).expect("prepare notifier query");
+
+    stmt.query_map([], |row| {
+        let intervene_id: String = row.get(0)?;
+        let state: String = row.get(1)?;
+        let slurm_id: Option<String> = row.get(2)?;
+        let exit_code: Option<i64> = row.get(3)?;
+        Ok((intervene_id, state, slurm_id, exit_code))
+    })
+        .expect("query pending notifications")
+        .filter_map(Result::ok)
+        .filter_map(|(intervene_id, state, slurm_id, exit_code)| {
+            JobState::from_db_str(&state)
+                .filter(|state| NOTIFIABLE_STATES.contains(state))
+                .map(|state| PendingNotification { intervene_id, state, slurm_id, exit_code })
+        })
+        .collect()
+}
+
+/// The path `sbatch --output` wrote the job's combined stdout/stderr to (see [`crate::db::job::update`])
+fn output_log_path(wd: &WorkingDirectory, intervene_id: &str, slurm_id: &str) -> String {
+    wd.path.join(intervene_id).join(format!("{slurm_id}.out")).to_string_lossy().into_owned()
+}
+
+/// POST the notification, retrying transport/5xx errors with exponential backoff (1s, 2s, 4s)
+async fn send_with_retry(config: &NotifierConfig, payload: &StateNotification<'_>) -> Result<(), String> {
+    let client = Client::new();
+    let mut backoff = Duration::from_secs(1);
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        let outcome = client.post(&config.endpoint).bearer_auth(&config.auth_token).json(payload).send().await;
+
+        match outcome {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) if !response.status().is_server_error() => {
+                return Err(format!("backend rejected notification: {}", response.status()));
+            }
+            Ok(response) => warn!("Notification attempt {attempt}/{MAX_ATTEMPTS} got {}", response.status()),
+            Err(e) => warn!("Notification attempt {attempt}/{MAX_ATTEMPTS} failed: {e}"),
+        }
+
+        if attempt < MAX_ATTEMPTS {
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(Duration::from_secs(4));
+        }
+    }
+
+    Err(format!("exhausted retry attempts notifying {} backend", config.namespace))
+}
+
+fn record_notified(conn: &Connection, id: &str, state: JobState) {
+    info!("Recording {id} notified of state {}", state.to_string());
+    conn.execute(
+        "UPDATE job SET last_notified_state = ?1 WHERE intervene_id = ?2",
+        (state.to_string(), id),
+    ).expect("Record notified state");
+}
@@ -2,7 +2,7 @@ use std::io;
 use std::io::ErrorKind;
 
 use jsonschema::JSONSchema;
-use log::{info, warn};
+use tracing::{info, warn};
 use rusoto_s3::S3;
 use serde_json::Value;
 use tokio::io::AsyncReadExt;
@@ -137,9 +137,10 @@ impl AllasMessage {
 
     /// Delete messages in the work queue
     ///
-    /// It's important to delete after the job has been ingested into the database. Jobs in the
-    /// database must have unique identifiers. Violating this constraint will currently cause a
-    /// panic.
+    /// It's important to delete after the job has been ingested into the database: re-ingesting
+    /// a message with an INTERVENE id that's already present is safe ([`crate::db::ingest::message::ingest_message`]
+    /// updates the existing row instead of failing), but deleting before ingest succeeds would
+    /// lose the message for good.
     pub async fn delete(&self, s3_client: &rusoto_s3::S3Client) {
         let bucket = self.bucket.to_string();
         let key = self.key.to_string();
@@ -4,4 +4,6 @@
 pub mod job_request;
 
 /// Read configuration templates and render them with message content
-pub mod job;
\ No newline at end of file
+pub mod job;
+/// Resolve SBATCH resource requests for a job
+pub mod resources;
\ No newline at end of file
@@ -4,11 +4,12 @@ use std::io::Write;
 use std::path::{Path, PathBuf};
 
 use chrono::Utc;
-use log::{info, warn};
+use tracing::{info, warn};
 use serde::Serialize;
 use tinytemplate::TinyTemplate;
 
 use crate::slurm::job_request::{GlobusDetails, JobRequest, NxfParamsFile, PipelineParam, TargetGenome};
+use crate::slurm::resources::{JobResourceDefaults, JobResources, JobResourcesBuilder};
 use crate::WorkingDirectory;
 
 /// A JobPath is the path to a job script that's submitted to SLURM via sbatch
@@ -23,7 +24,8 @@ pub struct JobPath {
 }
 
 impl JobRequest {
-    pub fn create(&self, wd: &WorkingDirectory, globus_path: &PathBuf) -> JobPath {
+    #[tracing::instrument(name = "create", skip_all, fields(intervene_id = %self.pipeline_param.id))]
+    pub fn create(&self, wd: &WorkingDirectory) -> JobPath {
         let instance_wd = WorkingDirectory { path: wd.path.join(&&self.pipeline_param.id) };
         info!("Creating job {} in working directory {}", &&self.pipeline_param.id, &instance_wd.path.display());
 
@@ -33,11 +35,18 @@ impl JobRequest {
         }
         fs::create_dir(&instance_wd.path).expect("Create working directory");
 
-        let header: Header = render_header(&&self.pipeline_param);
-        let callback: Callback = render_callback(&&self.pipeline_param);
+        let resources: JobResources = JobResourcesBuilder::from_pipeline_param(&self.pipeline_param)
+            .with_defaults(&JobResourceDefaults::from_env())
+            .build();
+
+        // write_transfer (below) writes the list of files to stage here; the nextflow workflow
+        // reads it to drive the globus transfer, landing the staged files in instance_wd itself
+        let globus_path = instance_wd.path.join("transfer.txt");
+
+        let header: Header = render_header(&&self.pipeline_param, &resources);
         let vars: EnvVars = read_environment_variables();
-        let workflow: Workflow = render_nxf(&globus_path, &&self.pipeline_param,  &wd.path);
-        let job = JobTemplate { header, callback, vars, workflow };
+        let workflow: Workflow = render_nxf(&globus_path, &&self.pipeline_param, &wd.path, &resources);
+        let job = JobTemplate { header, vars, workflow };
 
         let path = &instance_wd.path.join("job.sh");
         job.write(path).expect("Can't write job script");
@@ -51,9 +60,12 @@ impl JobRequest {
 }
 
 /// All rendered data necessary to submit an INTERVENE pgsc_calc job to SLURM
+///
+/// Job status is no longer reported from inside the job script (see [`crate::notifier`]):
+/// hattivatti notifies the INTERVENE backend itself once it observes a state transition, so the
+/// job script only needs to run the pipeline.
 struct JobTemplate {
     header: Header,
-    callback: Callback,
     vars: EnvVars,
     workflow: Workflow,
 }
@@ -69,7 +81,6 @@ impl JobTemplate {
         // order is important when writing the file
         let contents = [
             self.header.content,
-            self.callback.content,
             self.vars.content,
             self.workflow.content,
         ];
@@ -82,26 +93,11 @@ impl JobTemplate {
     }
 }
 
-/// Rendered HTTP callback
-///
-/// Uses curl to do a HTTP POST to the INTERVENE backend with job status. Currently supports two
-/// states depending on exit status: 0 (succeeded) or not 0 (failed). Uses a bash trap to callback
-/// when an error happens.
-struct Callback {
-    content: String,
-}
-
 /// Rendered SBATCH header
 ///
 /// SLURM jobs options can be parsed by sbatch using #SBATCH headers [before executable commands](https://slurm.schedmd.com/sbatch.html#SECTION_DESCRIPTION).
-/// Parts of the header should be set from message parameters, metadata, or CLI options, but only
-/// some are only implemented:
-/// - [X] job name
-/// - [ ] queue / partition (small)
-/// - [X] job time
-/// - [ ] local node storage (256gb)
-/// - [ ] job RAM
-/// - [ ] account for billing usage
+/// Job name, partition, wall time, memory, local node storage, and billing account are all
+/// resolved per job by [JobResourcesBuilder] and passed into [HeaderContext].
 ///
 /// Other options shouldn't be changed:
 /// - exclusive node execution
@@ -131,7 +127,11 @@ struct Workflow {
 #[derive(Serialize)]
 struct HeaderContext {
     name: String,
+    partition: String,
     job_time: String,
+    mem_gb: u32,
+    local_scratch_gb: u32,
+    account: String,
     time_now: String,
 }
 
@@ -153,12 +153,6 @@ struct NextflowContext {
     globus_parent_path: String
 }
 
-/// Rendering context for callback
-#[derive(Serialize)]
-struct CallbackContext {
-    name: String,
-}
-
 /// Write nextflow parameters to working directory
 fn write_config(nxf_params: &NxfParamsFile, wd: &WorkingDirectory) {
     let params_file: String = serde_json::to_string(nxf_params).expect("Deserialised");
@@ -186,7 +180,7 @@ fn write_allas(wd: &WorkingDirectory) {
 }
 
 /// Render the SBATCH header using TinyTemplate
-fn render_header(param: &PipelineParam) -> Header {
+fn render_header(param: &PipelineParam, resources: &JobResources) -> Header {
     /// included header template
     static HEADER: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/data/templates/header.txt"));
     let mut tt = TinyTemplate::new();
@@ -194,8 +188,11 @@ fn render_header(param: &PipelineParam) -> Header {
 
     let context = HeaderContext {
         name: param.id.to_string(),
-        // (todo: run job for 1 hour)
-        job_time: "01:00:00".to_string(),
+        partition: resources.partition.clone(),
+        job_time: resources.wall_time.clone(),
+        mem_gb: resources.mem_gb,
+        local_scratch_gb: resources.local_scratch_gb,
+        account: resources.account.clone(),
         time_now: Utc::now().to_string(),
     };
 
@@ -210,36 +207,22 @@ fn read_environment_variables() -> EnvVars {
 }
 
 /// Render the workflow commands using TinyTemplate
-fn render_nxf(globus_path: &PathBuf, param: &PipelineParam, work_dir: &Path) -> Workflow {
+fn render_nxf(globus_path: &PathBuf, param: &PipelineParam, work_dir: &Path, resources: &JobResources) -> Workflow {
     /// included workflow template
     static NXF: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/data/templates/nxf.txt"));
     let mut tt = TinyTemplate::new();
     tt.add_template("nxf", NXF).expect("Template");
     let name: &String = &param.id;
     let wd = work_dir.to_str().expect("path").to_string();
-    // todo: make dynamic based on deployment namespace
-    /// installation directory of pgsc_calc (TODO: make this a parameter)
-    static PGSC_CALC_DIR: &str = "/scratch/project_2004504/pgsc_calc/";
     let context = NextflowContext { name: name.clone(),
         work_dir: wd,
-        pgsc_calc_dir: PGSC_CALC_DIR.to_string(),
+        pgsc_calc_dir: resources.pgsc_calc_dir.clone(),
         globus_path: globus_path.to_str().expect("Globus path").to_string(),
         globus_parent_path: globus_path.parent().expect("Globus parent").to_str().expect("Globus parent path").to_string()
     };
     Workflow { content: tt.render("nxf", &context).expect("Rendered nextflow") }
 }
 
-/// Render the callback using TinyTemplate
-fn render_callback(param: &PipelineParam) -> Callback {
-    /// included callback template
-    static CALLBACK: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/data/templates/callback.txt"));
-    let mut tt = TinyTemplate::new();
-    tt.add_template("callback", CALLBACK).expect("Template");
-    let name: &String = &param.id;
-    let context = CallbackContext { name: name.clone() };
-    Callback { content: tt.render("callback", &context).expect("Rendered callback") }
-}
-
 /// Static nextflow configuration for publishing results to Allas
 struct AllasConfig {
     content: String,
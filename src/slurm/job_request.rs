@@ -6,6 +6,20 @@ pub struct PipelineParam {
     pub target_genomes: Vec<TargetGenome>,
     pub nxf_params_file: NxfParamsFile,
     pub nxf_work: String,
+    /// SBATCH resource overrides for this job. Any field left unset falls back to an environment
+    /// default, then a hardcoded value (see [crate::slurm::resources::JobResourcesBuilder]).
+    #[serde(default)]
+    pub resources: Option<JobResourcesRequest>,
+}
+
+/// Per-job SBATCH resource overrides, as supplied in an incoming message
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct JobResourcesRequest {
+    pub partition: Option<String>,
+    pub wall_time: Option<String>,
+    pub mem_gb: Option<u32>,
+    pub local_scratch_gb: Option<u32>,
+    pub account: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -0,0 +1,103 @@
+//! Resolve SBATCH resource requests for a job
+//!
+//! `render_header` used to hardcode `job_time` and `render_nxf` the pgsc_calc installation
+//! directory. [JobResourcesBuilder] resolves each field instead, in priority order: the incoming
+//! message's [JobResourcesRequest], then environment defaults (`HATTIVATTI_*` variables), then a
+//! hardcoded fallback. There are no command-line options for these defaults, and
+//! `pgsc_calc_dir` can't be set from a message.
+
+use crate::slurm::job_request::PipelineParam;
+
+/// Fallback values used when neither the message nor the environment specify a resource
+const DEFAULT_PARTITION: &str = "small";
+const DEFAULT_WALL_TIME: &str = "01:00:00";
+const DEFAULT_MEM_GB: u32 = 32;
+const DEFAULT_LOCAL_SCRATCH_GB: u32 = 256;
+const DEFAULT_ACCOUNT: &str = "project_2004504";
+const DEFAULT_PGSC_CALC_DIR: &str = "/scratch/project_2004504/pgsc_calc/";
+
+/// A fully resolved SBATCH resource request for a single job
+#[derive(Debug, Clone)]
+pub struct JobResources {
+    pub partition: String,
+    pub wall_time: String,
+    pub mem_gb: u32,
+    pub local_scratch_gb: u32,
+    pub account: String,
+    pub pgsc_calc_dir: String,
+}
+
+/// Environment-sourced defaults, used for any field a message doesn't set itself
+#[derive(Debug, Default)]
+pub struct JobResourceDefaults {
+    pub partition: Option<String>,
+    pub wall_time: Option<String>,
+    pub mem_gb: Option<u32>,
+    pub local_scratch_gb: Option<u32>,
+    pub account: Option<String>,
+    pub pgsc_calc_dir: Option<String>,
+}
+
+impl JobResourceDefaults {
+    /// Read defaults from environment variables, leaving unset variables as `None` so the
+    /// hardcoded fallback applies
+    pub fn from_env() -> JobResourceDefaults {
+        JobResourceDefaults {
+            partition: std::env::var("HATTIVATTI_PARTITION").ok(),
+            wall_time: std::env::var("HATTIVATTI_WALL_TIME").ok(),
+            mem_gb: std::env::var("HATTIVATTI_MEM_GB").ok().and_then(|v| v.parse().ok()),
+            local_scratch_gb: std::env::var("HATTIVATTI_LOCAL_SCRATCH_GB").ok().and_then(|v| v.parse().ok()),
+            account: std::env::var("HATTIVATTI_ACCOUNT").ok(),
+            pgsc_calc_dir: std::env::var("HATTIVATTI_PGSC_CALC_DIR").ok(),
+        }
+    }
+}
+
+/// Builds a [JobResources], resolving each field from the message, then environment defaults, then
+/// the hardcoded fallback
+pub struct JobResourcesBuilder {
+    partition: Option<String>,
+    wall_time: Option<String>,
+    mem_gb: Option<u32>,
+    local_scratch_gb: Option<u32>,
+    account: Option<String>,
+    pgsc_calc_dir: Option<String>,
+}
+
+impl JobResourcesBuilder {
+    /// Start from the resource overrides (if any) on a job's own message
+    pub fn from_pipeline_param(param: &PipelineParam) -> JobResourcesBuilder {
+        let requested = param.resources.as_ref();
+        JobResourcesBuilder {
+            partition: requested.and_then(|r| r.partition.clone()),
+            wall_time: requested.and_then(|r| r.wall_time.clone()),
+            mem_gb: requested.and_then(|r| r.mem_gb),
+            local_scratch_gb: requested.and_then(|r| r.local_scratch_gb),
+            account: requested.and_then(|r| r.account.clone()),
+            pgsc_calc_dir: None,
+        }
+    }
+
+    /// Fall back to environment defaults for any field the message didn't set
+    pub fn with_defaults(mut self, defaults: &JobResourceDefaults) -> JobResourcesBuilder {
+        self.partition = self.partition.or_else(|| defaults.partition.clone());
+        self.wall_time = self.wall_time.or_else(|| defaults.wall_time.clone());
+        self.mem_gb = self.mem_gb.or(defaults.mem_gb);
+        self.local_scratch_gb = self.local_scratch_gb.or(defaults.local_scratch_gb);
+        self.account = self.account.or_else(|| defaults.account.clone());
+        self.pgsc_calc_dir = self.pgsc_calc_dir.or_else(|| defaults.pgsc_calc_dir.clone());
+        self
+    }
+
+    /// Resolve any fields still unset to the hardcoded fallback
+    pub fn build(self) -> JobResources {
+        JobResources {
+            partition: self.partition.unwrap_or_else(|| DEFAULT_PARTITION.to_string()),
+            wall_time: self.wall_time.unwrap_or_else(|| DEFAULT_WALL_TIME.to_string()),
+            mem_gb: self.mem_gb.unwrap_or(DEFAULT_MEM_GB),
+            local_scratch_gb: self.local_scratch_gb.unwrap_or(DEFAULT_LOCAL_SCRATCH_GB),
+            account: self.account.unwrap_or_else(|| DEFAULT_ACCOUNT.to_string()),
+            pgsc_calc_dir: self.pgsc_calc_dir.unwrap_or_else(|| DEFAULT_PGSC_CALC_DIR.to_string()),
+        }
+    }
+}